@@ -0,0 +1,457 @@
+//! Enum types shared by the various ITCH message bodies.
+//!
+//! Each of these corresponds to a single-character (or small fixed-width)
+//! code in the wire format; the parsers in `lib.rs` map the raw ASCII byte
+//! onto the matching variant. `From`/`TryFrom` conversions to/from that raw
+//! `u8` code are derived for the single-byte enums below via the
+//! `itch_code_enum!` macro, which also wires up `Serialize`/`Deserialize`
+//! (behind the `serde` feature) so the wire-format code -- not the Rust
+//! variant name -- is what actually gets serialized. `IssueClassification`
+//! and `IssueSubType` are not built with that macro: they have no `u8`
+//! conversions, and their `Serialize`/`Deserialize` impls are the derived
+//! ones, which encode the variant rather than the wire code.
+
+use std::fmt;
+
+/// Returned by a `TryFrom<u8>` conversion when a byte doesn't match any
+/// known variant for that enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownCode(pub u8);
+
+impl fmt::Display for UnknownCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown ITCH code: {:#04x}", self.0)
+    }
+}
+
+impl ::std::error::Error for UnknownCode {}
+
+/// Serde support for the single-byte ITCH enum codes.
+///
+/// Rather than deriving `Serialize`/`Deserialize` (which would write out the
+/// verbose variant name), every enum in this module built with
+/// `itch_code_enum!` serializes as the same canonical `u8` code it's decoded
+/// from on the wire, via `Into<u8>` / `TryFrom<u8>`.
+#[cfg(feature = "serde")]
+mod codes {
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<u8>,
+        S: Serializer,
+    {
+        serializer.serialize_u8((*value).into())
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<u8>,
+        T::Error: fmt::Display,
+        D: Deserializer<'de>,
+    {
+        struct CodeVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for CodeVisitor<T>
+        where
+            T: TryFrom<u8>,
+            T::Error: fmt::Display,
+        {
+            type Value = T;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a single-byte ITCH code")
+            }
+
+            fn visit_u8<E>(self, value: u8) -> Result<T, E>
+            where
+                E: de::Error,
+            {
+                T::try_from(value).map_err(de::Error::custom)
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<T, E>
+            where
+                E: de::Error,
+            {
+                if value > 255 {
+                    return Err(de::Error::custom(format!("ITCH code {} out of range", value)));
+                }
+                self.visit_u8(value as u8)
+            }
+        }
+
+        deserializer.deserialize_u8(CodeVisitor(PhantomData))
+    }
+}
+
+/// Generates `From<$name> for u8`, `TryFrom<u8> for $name`, and (behind the
+/// `serde` feature) matching `Serialize`/`Deserialize` impls that go through
+/// those conversions instead of the variant name, for a C-like enum whose
+/// variants each correspond to one ITCH wire-format byte.
+macro_rules! itch_code_enum {
+    ($name:ident { $($variant:ident => $code:expr),+ $(,)* }) => {
+        impl From<$name> for u8 {
+            fn from(value: $name) -> u8 {
+                match value {
+                    $($name::$variant => $code),+
+                }
+            }
+        }
+
+        impl ::std::convert::TryFrom<u8> for $name {
+            type Error = UnknownCode;
+
+            fn try_from(code: u8) -> ::std::result::Result<$name, UnknownCode> {
+                match code {
+                    $($code => Ok($name::$variant),)+
+                    other => Err(UnknownCode(other)),
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl ::serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                codes::serialize(self, serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<$name, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                codes::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+itch_code_enum!(Side {
+    Buy => b'B',
+    Sell => b'S',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCode {
+    StartOfMessages,
+    StartOfSystemHours,
+    StartOfMarketHours,
+    EndOfMarketHours,
+    EndOfSystemHours,
+    EndOfMessages,
+}
+
+itch_code_enum!(EventCode {
+    StartOfMessages => b'O',
+    StartOfSystemHours => b'S',
+    StartOfMarketHours => b'Q',
+    EndOfMarketHours => b'M',
+    EndOfSystemHours => b'E',
+    EndOfMessages => b'C',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketCategory {
+    NasdaqGlobalSelect,
+    NasdaqGlobalMarket,
+    NasdaqCaptialMarket,
+    Nyse,
+    NyseMkt,
+    NyseArca,
+    BatsZExchange,
+    Unavailable,
+}
+
+itch_code_enum!(MarketCategory {
+    NasdaqGlobalSelect => b'Q',
+    NasdaqGlobalMarket => b'G',
+    NasdaqCaptialMarket => b'S',
+    Nyse => b'N',
+    NyseMkt => b'A',
+    NyseArca => b'P',
+    BatsZExchange => b'Z',
+    Unavailable => b' ',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinancialStatus {
+    Normal,
+    Deficient,
+    Delinquent,
+    Bankrupt,
+    Suspended,
+    DeficientBankrupt,
+    DeficientDelinquent,
+    DelinquentBankrupt,
+    DeficientDelinquentBankrupt,
+    EtpSuspended,
+    Unavailable,
+}
+
+itch_code_enum!(FinancialStatus {
+    Normal => b'N',
+    Deficient => b'D',
+    Delinquent => b'E',
+    Bankrupt => b'Q',
+    Suspended => b'S',
+    DeficientBankrupt => b'G',
+    DeficientDelinquent => b'H',
+    DelinquentBankrupt => b'J',
+    DeficientDelinquentBankrupt => b'K',
+    EtpSuspended => b'C',
+    Unavailable => b' ',
+});
+
+/// NASDAQ's code space is larger than the handful of mnemonics below, so any
+/// unrecognized code round-trips losslessly through `Other` instead of
+/// failing to parse (see `IssueSubType::from_wire` for the same approach).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueClassification {
+    AmericanDepositaryShare,
+    Bond,
+    CommonStock,
+    DepositoryReceipt,
+    Rule144A,
+    LimitedPartnership,
+    Notes,
+    OrdinaryShare,
+    PreferredStock,
+    OtherSecurities,
+    Right,
+    SharesOfBeneficialInterest,
+    ConvertibleDebenture,
+    Unit,
+    UnitsPerBenifInt,
+    Warrant,
+    /// A valid but unrecognized code, preserved verbatim as the raw wire
+    /// byte.
+    Other(u8),
+}
+
+impl IssueClassification {
+    /// Maps the raw wire byte onto a known mnemonic, or `Other` if the code
+    /// isn't one of the handful this crate knows by name.
+    pub(crate) fn from_wire(code: u8) -> IssueClassification {
+        match code {
+            b'A' => IssueClassification::AmericanDepositaryShare,
+            b'B' => IssueClassification::Bond,
+            b'C' => IssueClassification::CommonStock,
+            b'F' => IssueClassification::DepositoryReceipt,
+            b'I' => IssueClassification::Rule144A,
+            b'L' => IssueClassification::LimitedPartnership,
+            b'N' => IssueClassification::Notes,
+            b'O' => IssueClassification::OrdinaryShare,
+            b'P' => IssueClassification::PreferredStock,
+            b'Q' => IssueClassification::OtherSecurities,
+            b'R' => IssueClassification::Right,
+            b'S' => IssueClassification::SharesOfBeneficialInterest,
+            b'T' => IssueClassification::ConvertibleDebenture,
+            b'U' => IssueClassification::Unit,
+            b'V' => IssueClassification::UnitsPerBenifInt,
+            b'W' => IssueClassification::Warrant,
+            other => IssueClassification::Other(other),
+        }
+    }
+}
+
+/// Unlike the other codes in this module, Issue Sub-Type is a genuine
+/// two-byte code -- some codes are a single meaningful letter padded with a
+/// trailing space (`"C "` for Common), others use both bytes (`"AI"` for
+/// Alpha Index ETNs). NASDAQ's code space is large and keeps growing, so
+/// only the handful of mnemonics below get a named variant; any other pair
+/// of bytes round-trips losslessly through `Other` instead of failing to
+/// parse.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueSubType {
+    AdditionalClassA,
+    AlphaIndexETNs,
+    Bankruptcy,
+    Common,
+    CommodityBasedTrustShares,
+    EquityGoldShares,
+    Index,
+    LimitedPartnership,
+    NonRestricted,
+    OpenEndedFund,
+    PreferredTrustSecurityRedeemable,
+    Right,
+    Unit,
+    Warrant,
+    ExchangeTradedNote,
+    /// A valid but unrecognized code, preserved verbatim as the two raw
+    /// wire bytes.
+    Other(u8, u8),
+}
+
+impl IssueSubType {
+    /// Maps the two raw wire bytes onto a known mnemonic, or `Other` if the
+    /// code isn't one of the handful this crate knows by name.
+    pub(crate) fn from_wire(first: u8, second: u8) -> IssueSubType {
+        match (first, second) {
+            (b'A', b' ') => IssueSubType::AdditionalClassA,
+            (b'A', b'I') => IssueSubType::AlphaIndexETNs,
+            (b'B', b' ') => IssueSubType::Bankruptcy,
+            (b'C', b' ') => IssueSubType::Common,
+            (b'C', b'B') => IssueSubType::CommodityBasedTrustShares,
+            (b'E', b'G') => IssueSubType::EquityGoldShares,
+            (b'I', b' ') => IssueSubType::Index,
+            (b'L', b' ') => IssueSubType::LimitedPartnership,
+            (b'N', b' ') => IssueSubType::NonRestricted,
+            (b'O', b' ') => IssueSubType::OpenEndedFund,
+            (b'P', b' ') => IssueSubType::PreferredTrustSecurityRedeemable,
+            (b'R', b' ') => IssueSubType::Right,
+            (b'U', b' ') => IssueSubType::Unit,
+            (b'W', b' ') => IssueSubType::Warrant,
+            (b'Z', b' ') => IssueSubType::ExchangeTradedNote,
+            (first, second) => IssueSubType::Other(first, second),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LuldRefPriceTier {
+    Na,
+    Tier1,
+    Tier2,
+}
+
+itch_code_enum!(LuldRefPriceTier {
+    Na => b' ',
+    Tier1 => b'1',
+    Tier2 => b'2',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegShoAction {
+    None,
+    Intraday,
+    Extant,
+}
+
+itch_code_enum!(RegShoAction {
+    None => b'0',
+    Intraday => b'1',
+    Extant => b'2',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingState {
+    Halted,
+    Paused,
+    QuotationOnly,
+    Trading,
+}
+
+itch_code_enum!(TradingState {
+    Halted => b'H',
+    Paused => b'P',
+    QuotationOnly => b'Q',
+    Trading => b'T',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketMakerMode {
+    Normal,
+    Passive,
+    Syndicate,
+    Presyndicate,
+    Penalty,
+}
+
+itch_code_enum!(MarketMakerMode {
+    Normal => b'N',
+    Passive => b'P',
+    Syndicate => b'S',
+    Presyndicate => b'R',
+    Penalty => b'L',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketParticipantState {
+    Active,
+    Excused,
+    Withdrawn,
+    Suspended,
+    Deleted,
+}
+
+itch_code_enum!(MarketParticipantState {
+    Active => b'A',
+    Excused => b'E',
+    Withdrawn => b'W',
+    Suspended => b'S',
+    Deleted => b'D',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossType {
+    Opening,
+    Closing,
+    HaltOrIpo,
+    Intraday,
+}
+
+itch_code_enum!(CrossType {
+    Opening => b'O',
+    Closing => b'C',
+    HaltOrIpo => b'H',
+    Intraday => b'I',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImbalanceDirection {
+    Buy,
+    Sell,
+    NoImbalance,
+    InsufficientOrders,
+}
+
+itch_code_enum!(ImbalanceDirection {
+    Buy => b'B',
+    Sell => b'S',
+    NoImbalance => b'N',
+    InsufficientOrders => b'O',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpoReleaseQualifier {
+    Anticipated,
+    CanceledOrPostponed,
+}
+
+itch_code_enum!(IpoReleaseQualifier {
+    Anticipated => b'A',
+    CanceledOrPostponed => b'C',
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreachedLevel {
+    Level1,
+    Level2,
+    Level3,
+}
+
+itch_code_enum!(BreachedLevel {
+    Level1 => b'1',
+    Level2 => b'2',
+    Level3 => b'3',
+});
@@ -4,8 +4,21 @@ extern crate error_chain;
 extern crate nom;
 extern crate flate2;
 extern crate arrayvec;
+extern crate memmap;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate bincode;
+#[cfg(feature = "rust_decimal")]
+extern crate rust_decimal;
+#[cfg(feature = "csv")]
+extern crate csv;
 
 use std::io::prelude::*;
+use std::io::ErrorKind;
 use std::fs::File;
 use std::path::Path;
 use std::fmt;
@@ -20,6 +33,15 @@ pub use enums::*;
 const BUFSIZE: usize = 200;
 
 mod enums;
+mod mold;
+mod mmap;
+mod price;
+
+pub use mold::{parse_pcap, MoldMessage, MoldStream, PcapStream};
+pub use mmap::{parse_mmap, AddOrderMpidRef, AddOrderRef, CrossTradeRef, IpoQuotingPeriodRef,
+                LuldAuctionCollarRef, MarketParticipantPositionRef, MessageBodyRef, MessageRef,
+                MmapFile, MmapMessages, NoiiRef, StockDirectoryRef, TradeRef};
+pub use price::Price4;
 
 
 #[allow(unused_doc_comment)]
@@ -159,17 +181,233 @@ named!(maybe_char2bool<Option<bool>>, alt!(
     char!(' ') => {|_| None}
 ));
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Message {
-    header: MsgHeader,
-    body: MessageBody,
+    pub header: MsgHeader,
+    pub body: MessageBody,
+}
+
+#[cfg(feature = "serde")]
+impl Message {
+    /// Re-encodes this message as bincode, using the compact single-byte
+    /// codes for the `enums` built with `itch_code_enum!`;
+    /// `IssueClassification` and `IssueSubType` use their derived encoding.
+    pub fn to_bincode(&self) -> Vec<u8> {
+        ::bincode::serialize(self).expect("Message serialization is infallible")
+    }
+
+    /// The `to_bincode` companion: decodes a `Message` back out of its
+    /// bincode encoding.
+    pub fn from_bincode(bytes: &[u8]) -> Result<Message> {
+        ::bincode::deserialize(bytes).chain_err(|| "Failed to decode bincode-encoded Message")
+    }
+}
+
+/// Re-encodes every message of a stream as length-prefixed bincode records,
+/// so a whole `MessageStream` can be transcoded to a compact binary form in
+/// one pass for downstream pipelines.
+#[cfg(feature = "serde")]
+pub fn write_bincode<R: Read, W: Write>(stream: MessageStream<R>, writer: &mut W) -> Result<()> {
+    for message in stream {
+        let encoded = message?.to_bincode();
+        writer.write_all(&(encoded.len() as u32).to_be_bytes())?;
+        writer.write_all(&encoded)?;
+    }
+    Ok(())
+}
+
+/// The `write_bincode` companion: reads back a stream of length-prefixed
+/// bincode records written by it.
+#[cfg(feature = "serde")]
+pub struct BincodeStream<R> {
+    reader: R,
+}
+
+#[cfg(feature = "serde")]
+impl<R: Read> Iterator for BincodeStream<R> {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Result<Message>> {
+        let mut len_bytes = [0; 4];
+        match self.reader.read_exact(&mut len_bytes) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e.into())),
+        }
+        let mut encoded = vec![0; u32::from_be_bytes(len_bytes) as usize];
+        if let Err(e) = self.reader.read_exact(&mut encoded) {
+            return Some(Err(e.into()));
+        }
+        Some(Message::from_bincode(&encoded))
+    }
+}
+
+/// Reads a stream of length-prefixed bincode records back into `Message`s,
+/// the same framing `write_bincode` produces.
+#[cfg(feature = "serde")]
+pub fn read_bincode<R: Read>(reader: R) -> BincodeStream<R> {
+    BincodeStream { reader }
+}
+
+const CSV_HEADER: &[&str] = &[
+    "timestamp", "stock_locate", "tag", "side", "shares", "stock", "price",
+    "reference", "new_reference", "match_number",
+];
+
+/// Flattens a `MessageStream` into CSV rows for analysis tooling. Every row
+/// shares the same columns; fields that don't apply to a given message
+/// type (e.g. `price` for a `SystemEvent`) are left blank rather than
+/// reshaping the schema per message type. Prices go through `Price4`'s
+/// `Display` so the CSV loads straight into a dataframe without a
+/// post-processing pass to rescale them.
+#[cfg(feature = "csv")]
+pub fn write_csv<R: Read, W: Write>(stream: MessageStream<R>, writer: W) -> Result<()> {
+    let mut csv_writer = ::csv::Writer::from_writer(writer);
+    csv_writer
+        .write_record(CSV_HEADER)
+        .chain_err(|| "Failed to write CSV header")?;
+    for message in stream {
+        csv_writer
+            .write_record(&message_to_csv_record(&message?))
+            .chain_err(|| "Failed to write CSV record")?;
+    }
+    csv_writer.flush().chain_err(|| "Failed to flush CSV writer")
+}
+
+#[cfg(feature = "csv")]
+fn message_to_csv_record(message: &Message) -> [String; 10] {
+    let mut side = String::new();
+    let mut shares = String::new();
+    let mut stock = String::new();
+    let mut price = String::new();
+    let mut reference = String::new();
+    let mut new_reference = String::new();
+    let mut match_number = String::new();
+
+    let tag = match message.body {
+        MessageBody::SystemEvent { .. } => 'S',
+        MessageBody::StockDirectory(ref sd) => {
+            stock = sd.stock.to_string();
+            'R'
+        }
+        MessageBody::ParticipantPosition(ref pp) => {
+            stock = pp.stock.to_string();
+            'L'
+        }
+        MessageBody::RegShoRestriction { stock: ref s, .. } => {
+            stock = s.to_string();
+            'Y'
+        }
+        MessageBody::TradingAction { stock: ref s, .. } => {
+            stock = s.to_string();
+            'H'
+        }
+        MessageBody::AddOrder(ref order) => {
+            side = format!("{:?}", order.side);
+            shares = order.shares.to_string();
+            stock = order.stock.to_string();
+            price = order.price.to_string();
+            reference = order.reference.to_string();
+            'A'
+        }
+        MessageBody::AddOrderMpid(ref order) => {
+            side = format!("{:?}", order.side);
+            shares = order.shares.to_string();
+            stock = order.stock.to_string();
+            price = order.price.to_string();
+            reference = order.reference.to_string();
+            'F'
+        }
+        MessageBody::ReplaceOrder(ref order) => {
+            shares = order.shares.to_string();
+            price = order.price.to_string();
+            reference = order.old_reference.to_string();
+            new_reference = order.new_reference.to_string();
+            'U'
+        }
+        MessageBody::DeleteOrder { reference: r } => {
+            reference = r.to_string();
+            'D'
+        }
+        MessageBody::OrderExecuted(order) => {
+            shares = order.executed_shares.to_string();
+            reference = order.reference.to_string();
+            match_number = order.match_number.to_string();
+            'E'
+        }
+        MessageBody::OrderExecutedWithPrice(order) => {
+            shares = order.executed_shares.to_string();
+            price = order.execution_price.to_string();
+            reference = order.reference.to_string();
+            match_number = order.match_number.to_string();
+            'C'
+        }
+        MessageBody::OrderCancel(order) => {
+            shares = order.cancelled_shares.to_string();
+            reference = order.reference.to_string();
+            'X'
+        }
+        MessageBody::Trade(ref trade) => {
+            side = format!("{:?}", trade.side);
+            shares = trade.shares.to_string();
+            stock = trade.stock.to_string();
+            price = trade.price.to_string();
+            reference = trade.reference.to_string();
+            match_number = trade.match_number.to_string();
+            'P'
+        }
+        MessageBody::CrossTrade(ref trade) => {
+            shares = trade.shares.to_string();
+            stock = trade.stock.to_string();
+            price = trade.cross_price.to_string();
+            match_number = trade.match_number.to_string();
+            'Q'
+        }
+        MessageBody::BrokenTrade { match_number: m } => {
+            match_number = m.to_string();
+            'B'
+        }
+        MessageBody::Noii(ref noii) => {
+            stock = noii.stock.to_string();
+            price = noii.current_reference_price.to_string();
+            'I'
+        }
+        MessageBody::MwcbDeclineLevel(_) => 'V',
+        MessageBody::MwcbBreach(_) => 'W',
+        MessageBody::IpoQuotingPeriod(ref ipo) => {
+            stock = ipo.stock.to_string();
+            price = ipo.ipo_price.to_string();
+            'K'
+        }
+        MessageBody::LuldAuctionCollar(ref collar) => {
+            stock = collar.stock.to_string();
+            price = collar.reference_price.to_string();
+            'J'
+        }
+        MessageBody::Unknown { tag, .. } => tag,
+    };
+
+    [
+        message.header.timestamp.to_string(),
+        message.header.stock_locate.to_string(),
+        tag.to_string(),
+        side,
+        shares,
+        stock,
+        price,
+        reference,
+        new_reference,
+        match_number,
+    ]
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
-struct MsgHeader {
-    stock_locate: u16,
-    tracking_number: u16,
-    timestamp: u64,
+pub struct MsgHeader {
+    pub stock_locate: u16,
+    pub tracking_number: u16,
+    pub timestamp: u64,
 }
 
 named!(parse_message_header<MsgHeader>, do_parse!(
@@ -180,9 +418,11 @@ named!(parse_message_header<MsgHeader>, do_parse!(
 ));
 
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
 pub enum MessageBody {
     AddOrder(AddOrder),
+    AddOrderMpid(AddOrderMpid),
     ReplaceOrder(ReplaceOrder),
     DeleteOrder { reference: u64 },
     SystemEvent { event: EventCode },
@@ -197,6 +437,17 @@ pub enum MessageBody {
     },
     StockDirectory(StockDirectory),
     ParticipantPosition(MarketParticipantPosition),
+    OrderExecuted(OrderExecuted),
+    OrderExecutedWithPrice(OrderExecutedWithPrice),
+    OrderCancel(OrderCancel),
+    Trade(Trade),
+    CrossTrade(CrossTrade),
+    BrokenTrade { match_number: u64 },
+    Noii(Noii),
+    MwcbDeclineLevel(MwcbDeclineLevel),
+    MwcbBreach(MwcbBreach),
+    IpoQuotingPeriod(IpoQuotingPeriod),
+    LuldAuctionCollar(LuldAuctionCollar),
     Unknown {
         length: u16,
         tag: char,
@@ -206,25 +457,49 @@ pub enum MessageBody {
 
 named!(parse_message<Message>, do_parse!(
     length: be_u16 >>
-    tag: be_u8 >>
-    header: parse_message_header >>
-    body: switch!(value!(tag),  // TODO is this 'value' call necessary?
-        b'S' => call!(parse_system_event) |
-        b'R' => map!(parse_stock_directory, |sd| MessageBody::StockDirectory(sd)) |
-        b'L' => map!(parse_participant_position, |pp| MessageBody::ParticipantPosition(pp)) |
-        b'Y' => call!(parse_reg_sho_restriction) |
-        b'H' => call!(parse_trading_action) |
-        b'A' => map!(parse_add_order, |order| MessageBody::AddOrder(order)) |
-        b'U' => map!(parse_replace_order, |order| MessageBody::ReplaceOrder(order)) |
-        b'D' => map!(be_u64, |reference| MessageBody::DeleteOrder{ reference }) |
-        other => map!(take!(length - 11),    // tag + header = 11
-                      |slice| MessageBody::Unknown {
-                          length, tag: other as char, content: Vec::from(slice)
-        })) >>
-    (Message { header, body })
+    message: call!(parse_message_body, length) >>
+    (message)
 ));
 
+// Pulled out of `parse_message` so that callers who already have the length
+// and the tag+header+body bytes in hand (e.g. MoldUDP64 message blocks,
+// which carry their own external length prefix instead of ITCH's embedded
+// one) can decode without re-synthesizing a fake length prefix.
+fn parse_message_body(input: &[u8], length: u16) -> IResult<&[u8], Message> {
+    do_parse!(input,
+        tag: be_u8 >>
+        header: parse_message_header >>
+        body: switch!(value!(tag),  // TODO is this 'value' call necessary?
+            b'S' => call!(parse_system_event) |
+            b'R' => map!(parse_stock_directory, |sd| MessageBody::StockDirectory(sd)) |
+            b'L' => map!(parse_participant_position, |pp| MessageBody::ParticipantPosition(pp)) |
+            b'Y' => call!(parse_reg_sho_restriction) |
+            b'H' => call!(parse_trading_action) |
+            b'A' => map!(parse_add_order, |order| MessageBody::AddOrder(order)) |
+            b'F' => map!(parse_add_order_mpid, |order| MessageBody::AddOrderMpid(order)) |
+            b'U' => map!(parse_replace_order, |order| MessageBody::ReplaceOrder(order)) |
+            b'D' => map!(be_u64, |reference| MessageBody::DeleteOrder{ reference }) |
+            b'E' => map!(parse_order_executed, |e| MessageBody::OrderExecuted(e)) |
+            b'C' => map!(parse_order_executed_with_price, |e| MessageBody::OrderExecutedWithPrice(e)) |
+            b'X' => map!(parse_order_cancel, |c| MessageBody::OrderCancel(c)) |
+            b'P' => map!(parse_trade, |t| MessageBody::Trade(t)) |
+            b'Q' => map!(parse_cross_trade, |t| MessageBody::CrossTrade(t)) |
+            b'B' => map!(be_u64, |match_number| MessageBody::BrokenTrade { match_number }) |
+            b'I' => map!(parse_noii, |n| MessageBody::Noii(n)) |
+            b'V' => map!(parse_mwcb_decline_level, |l| MessageBody::MwcbDeclineLevel(l)) |
+            b'W' => map!(parse_mwcb_breach, |b| MessageBody::MwcbBreach(b)) |
+            b'K' => map!(parse_ipo_quoting_period, |k| MessageBody::IpoQuotingPeriod(k)) |
+            b'J' => map!(parse_luld_auction_collar, |j| MessageBody::LuldAuctionCollar(j)) |
+            other => map!(take!(length - 11),    // tag + header = 11
+                          |slice| MessageBody::Unknown {
+                              length, tag: other as char, content: Vec::from(slice)
+            })) >>
+        (Message { header, body })
+    )
+}
+
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct StockDirectory {
     stock: ArrayString<[u8; 8]>,
@@ -243,60 +518,111 @@ pub struct StockDirectory {
     inverse_indicator: bool,
 }
 
+// These per-field sub-parsers are pulled out (instead of inlining the
+// `alt!` chains directly in the message parsers below) so that the
+// zero-copy `MessageRef` parsers in `mmap` can share them verbatim.
+named!(parse_event_code<EventCode>, alt!(
+    char!('O') => { |_| EventCode::StartOfMessages } |
+    char!('S') => { |_| EventCode::StartOfSystemHours } |
+    char!('Q') => { |_| EventCode::StartOfMarketHours } |
+    char!('M') => { |_| EventCode::EndOfMarketHours } |
+    char!('E') => { |_| EventCode::EndOfSystemHours } |
+    char!('C') => { |_| EventCode::EndOfMessages }
+));
+
+named!(parse_market_category<MarketCategory>, alt!(
+    char!('Q') => { |_| MarketCategory::NasdaqGlobalSelect } |
+    char!('G') => { |_| MarketCategory::NasdaqGlobalMarket } |
+    char!('S') => { |_| MarketCategory::NasdaqCaptialMarket } |
+    char!('N') => { |_| MarketCategory::Nyse } |
+    char!('A') => { |_| MarketCategory::NyseMkt } |
+    char!('P') => { |_| MarketCategory::NyseArca } |
+    char!('Z') => { |_| MarketCategory::BatsZExchange } |
+    char!(' ') => { |_| MarketCategory::Unavailable }
+));
+
+named!(parse_financial_status<FinancialStatus>, alt!(
+    char!('N') => { |_| FinancialStatus::Normal } |
+    char!('D') => { |_| FinancialStatus::Deficient } |
+    char!('E') => { |_| FinancialStatus::Delinquent } |
+    char!('Q') => { |_| FinancialStatus::Bankrupt } |
+    char!('S') => { |_| FinancialStatus::Suspended } |
+    char!('G') => { |_| FinancialStatus::DeficientBankrupt } |
+    char!('H') => { |_| FinancialStatus::DeficientDelinquent } |
+    char!('J') => { |_| FinancialStatus::DelinquentBankrupt } |
+    char!('K') => { |_| FinancialStatus::DeficientDelinquentBankrupt } |
+    char!('C') => { |_| FinancialStatus::EtpSuspended } |
+    char!(' ') => { |_| FinancialStatus::Unavailable }
+));
+
+named!(parse_authenticity<bool>, alt!(
+    char!('P') => {|_| true} |
+    char!('T') => {|_| false}
+));
+
+named!(parse_luld_ref_price_tier<LuldRefPriceTier>, alt!(
+    char!(' ') => { |_| LuldRefPriceTier::Na } |
+    char!('1') => { |_| LuldRefPriceTier::Tier1 } |
+    char!('2') => { |_| LuldRefPriceTier::Tier2 }
+));
+
+// Unrecognized codes round-trip losslessly through `Other` (see
+// `IssueClassification::from_wire`), so this can't fail to parse.
+named!(parse_issue_classification<IssueClassification>, map!(
+    be_u8, IssueClassification::from_wire
+));
+
+// Both bytes of the field carry meaning (see `IssueSubType::from_wire`), so
+// this just reads them verbatim -- the mapping to a known mnemonic (or
+// `Other`) happens afterward and can't fail.
+named!(parse_issue_sub_type<IssueSubType>, do_parse!(
+    first: be_u8 >>
+    second: be_u8 >>
+    (IssueSubType::from_wire(first, second))
+));
+
+named!(parse_cross_type<CrossType>, alt!(
+    char!('O') => { |_| CrossType::Opening } |
+    char!('C') => { |_| CrossType::Closing } |
+    char!('H') => { |_| CrossType::HaltOrIpo } |
+    char!('I') => { |_| CrossType::Intraday }
+));
+
+named!(parse_imbalance_direction<ImbalanceDirection>, alt!(
+    char!('B') => { |_| ImbalanceDirection::Buy } |
+    char!('S') => { |_| ImbalanceDirection::Sell } |
+    char!('N') => { |_| ImbalanceDirection::NoImbalance } |
+    char!('O') => { |_| ImbalanceDirection::InsufficientOrders }
+));
+
+named!(parse_ipo_release_qualifier<IpoReleaseQualifier>, alt!(
+    char!('A') => { |_| IpoReleaseQualifier::Anticipated } |
+    char!('C') => { |_| IpoReleaseQualifier::CanceledOrPostponed }
+));
+
+named!(parse_breached_level<BreachedLevel>, alt!(
+    char!('1') => { |_| BreachedLevel::Level1 } |
+    char!('2') => { |_| BreachedLevel::Level2 } |
+    char!('3') => { |_| BreachedLevel::Level3 }
+));
+
 named!(parse_system_event<MessageBody>, do_parse!(
-    event_code: alt!(
-        char!('O') => { |_| EventCode::StartOfMessages } |
-        char!('S') => { |_| EventCode::StartOfSystemHours } |
-        char!('Q') => { |_| EventCode::StartOfMarketHours } |
-        char!('M') => { |_| EventCode::EndOfMarketHours } |
-        char!('E') => { |_| EventCode::EndOfSystemHours } |
-        char!('C') => { |_| EventCode::EndOfMessages }
-    ) >>
+    event_code: parse_event_code >>
     (MessageBody::SystemEvent{event: event_code})
 ));
 
 named!(parse_stock_directory<StockDirectory>, do_parse!(
     stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
-    market_category: alt!(
-        char!('Q') => { |_| MarketCategory::NasdaqGlobalSelect } |
-        char!('G') => { |_| MarketCategory::NasdaqGlobalMarket } |
-        char!('S') => { |_| MarketCategory::NasdaqCaptialMarket } |
-        char!('N') => { |_| MarketCategory::Nyse } |
-        char!('A') => { |_| MarketCategory::NyseMkt } |
-        char!('P') => { |_| MarketCategory::NyseArca } |
-        char!('Z') => { |_| MarketCategory::BatsZExchange } |
-        char!(' ') => { |_| MarketCategory::Unavailable }
-    ) >>
-    financial_status: alt!(
-        char!('N') => { |_| FinancialStatus::Normal } |
-        char!('D') => { |_| FinancialStatus::Deficient } |
-        char!('E') => { |_| FinancialStatus::Delinquent } |
-        char!('Q') => { |_| FinancialStatus::Bankrupt } |
-        char!('S') => { |_| FinancialStatus::Suspended } |
-        char!('G') => { |_| FinancialStatus::DeficientBankrupt } |
-        char!('H') => { |_| FinancialStatus::DeficientDelinquent } |
-        char!('J') => { |_| FinancialStatus::DelinquentBankrupt } |
-        char!('K') => { |_| FinancialStatus::DeficientDelinquentBankrupt } |
-        char!('C') => { |_| FinancialStatus::EtpSuspended } |
-        char!(' ') => { |_| FinancialStatus::Unavailable }
-    ) >>
+    market_category: parse_market_category >>
+    financial_status: parse_financial_status >>
     round_lot_size: be_u32 >>
     round_lots_only: char2bool >>
-
-    // FIXME these are dummy values
-    issue_classification: value!(IssueClassification::Unit, take!(1)) >>
-    issue_subtype: value!(IssueSubType::AlphaIndexETNs, take!(2)) >>
-    authenticity: alt!(
-        char!('P') => {|_| true} |
-        char!('T') => {|_| false}
-    ) >>
+    issue_classification: parse_issue_classification >>
+    issue_subtype: parse_issue_sub_type >>
+    authenticity: parse_authenticity >>
     short_sale_threshold: maybe_char2bool >>
     ipo_flag: maybe_char2bool >>
-    luld_ref_price_tier: alt!(
-        char!(' ') => { |_| LuldRefPriceTier::Na } |
-        char!('1') => { |_| LuldRefPriceTier::Tier1 } |
-        char!('2') => { |_| LuldRefPriceTier::Tier2 }
-    ) >>
+    luld_ref_price_tier: parse_luld_ref_price_tier >>
     etp_flag: maybe_char2bool >>
     etp_leverage_factor: be_u32 >>
     inverse_indicator: char2bool >>
@@ -308,6 +634,7 @@ named!(parse_stock_directory<StockDirectory>, do_parse!(
     })
 ));
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MarketParticipantPosition {
     mpid: ArrayString<[u8; 4]>,
@@ -317,24 +644,28 @@ pub struct MarketParticipantPosition {
     market_participant_state: MarketParticipantState,
 }
 
+named!(parse_market_maker_mode<MarketMakerMode>, alt!(
+    char!('N') => {|_| MarketMakerMode::Normal} |
+    char!('P') => {|_| MarketMakerMode::Passive} |
+    char!('S') => {|_| MarketMakerMode::Syndicate} |
+    char!('R') => {|_| MarketMakerMode::Presyndicate} |
+    char!('L') => {|_| MarketMakerMode::Penalty}
+));
+
+named!(parse_market_participant_state<MarketParticipantState>, alt!(
+    char!('A') => {|_| MarketParticipantState::Active} |
+    char!('E') => {|_| MarketParticipantState::Excused} |
+    char!('W') => {|_| MarketParticipantState::Withdrawn} |
+    char!('S') => {|_| MarketParticipantState::Suspended} |
+    char!('D') => {|_| MarketParticipantState::Deleted}
+));
+
 named!(parse_participant_position<MarketParticipantPosition>, do_parse!(
     mpid: map!(take_str!(4), |s| ArrayString::from(s).unwrap()) >>
     stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
     primary_market_maker: char2bool >>
-    market_maker_mode: alt!(
-        char!('N') => {|_| MarketMakerMode::Normal} |
-        char!('P') => {|_| MarketMakerMode::Passive} |
-        char!('S') => {|_| MarketMakerMode::Syndicate} |
-        char!('R') => {|_| MarketMakerMode::Presyndicate} |
-        char!('L') => {|_| MarketMakerMode::Penalty}
-    ) >>
-    market_participant_state: alt!(
-        char!('A') => {|_| MarketParticipantState::Active} |
-        char!('E') => {|_| MarketParticipantState::Excused} |
-        char!('W') => {|_| MarketParticipantState::Withdrawn} |
-        char!('S') => {|_| MarketParticipantState::Suspended} |
-        char!('D') => {|_| MarketParticipantState::Deleted}
-    ) >>
+    market_maker_mode: parse_market_maker_mode >>
+    market_participant_state: parse_market_participant_state >>
     (MarketParticipantPosition{
             mpid,
             stock,
@@ -344,66 +675,276 @@ named!(parse_participant_position<MarketParticipantPosition>, do_parse!(
     })
 ));
 
+named!(parse_reg_sho_action<RegShoAction>, alt!(
+    char!('0') => {|_| RegShoAction::None} |
+    char!('1') => {|_| RegShoAction::Intraday} |
+    char!('2') => {|_| RegShoAction::Extant}
+));
+
 named!(parse_reg_sho_restriction<MessageBody>, do_parse!(
     stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
-    action: alt!(
-        char!('0') => {|_| RegShoAction::None} |
-        char!('1') => {|_| RegShoAction::Intraday} |
-        char!('2') => {|_| RegShoAction::Extant}
-    ) >>
+    action: parse_reg_sho_action >>
     (MessageBody::RegShoRestriction { stock, action })
 ));
 
+named!(parse_trading_state<TradingState>, alt!(
+    char!('H') => {|_| TradingState::Halted} |
+    char!('P') => {|_| TradingState::Paused} |
+    char!('Q') => {|_| TradingState::QuotationOnly} |
+    char!('T') => {|_| TradingState::Trading}
+));
+
 named!(parse_trading_action<MessageBody>, do_parse!(
     stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
-    trading_state: alt!(
-        char!('H') => {|_| TradingState::Halted} |
-        char!('P') => {|_| TradingState::Paused} |
-        char!('Q') => {|_| TradingState::QuotationOnly} |
-        char!('T') => {|_| TradingState::Trading}
-    ) >> be_u8 >> // skip reserved byte
+    trading_state: parse_trading_state >>
+    be_u8 >> // skip reserved byte
     reason: map!(take_str!(4), |s| ArrayString::from(s).unwrap()) >>
     (MessageBody::TradingAction { stock, trading_state, reason })
 ));
 
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct AddOrder {
     reference: u64,
     side: Side,
     shares: u32,
     stock: ArrayString<[u8; 8]>,
-    price: u32,
+    price: Price4,
 }
 
+named!(parse_side<Side>, alt!(
+    char!('B') => {|_| Side::Buy} |
+    char!('S') => {|_| Side::Sell}
+));
+
 named!(parse_add_order<AddOrder>, do_parse!(
     reference: be_u64 >>
-    side: alt!(
-        char!('B') => {|_| Side::Buy} |
-        char!('S') => {|_| Side::Sell}
-    ) >>
+    side: parse_side >>
     shares: be_u32 >>
     stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
-    price: be_u32 >>
+    price: map!(be_u32, Price4::from_raw) >>
     (AddOrder { reference, side, shares, stock, price })
 ));
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddOrderMpid {
+    reference: u64,
+    side: Side,
+    shares: u32,
+    stock: ArrayString<[u8; 8]>,
+    price: Price4,
+    mpid: ArrayString<[u8; 4]>,
+}
+
+named!(parse_add_order_mpid<AddOrderMpid>, do_parse!(
+    reference: be_u64 >>
+    side: parse_side >>
+    shares: be_u32 >>
+    stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
+    price: map!(be_u32, Price4::from_raw) >>
+    mpid: map!(take_str!(4), |s| ArrayString::from(s).unwrap()) >>
+    (AddOrderMpid { reference, side, shares, stock, price, mpid })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReplaceOrder {
     old_reference: u64,
     new_reference: u64,
     shares: u32,
-    price: u32,
+    price: Price4,
 }
 
 named!(parse_replace_order<ReplaceOrder>, do_parse!(
     old_reference: be_u64 >>
     new_reference: be_u64 >>
     shares: be_u32 >>
-    price: be_u32 >>
+    price: map!(be_u32, Price4::from_raw) >>
     (ReplaceOrder { old_reference, new_reference, shares, price })
 ));
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderExecuted {
+    reference: u64,
+    executed_shares: u32,
+    match_number: u64,
+}
+
+named!(parse_order_executed<OrderExecuted>, do_parse!(
+    reference: be_u64 >>
+    executed_shares: be_u32 >>
+    match_number: be_u64 >>
+    (OrderExecuted { reference, executed_shares, match_number })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderExecutedWithPrice {
+    reference: u64,
+    executed_shares: u32,
+    match_number: u64,
+    printable: bool,
+    execution_price: Price4,
+}
+
+named!(parse_order_executed_with_price<OrderExecutedWithPrice>, do_parse!(
+    reference: be_u64 >>
+    executed_shares: be_u32 >>
+    match_number: be_u64 >>
+    printable: char2bool >>
+    execution_price: map!(be_u32, Price4::from_raw) >>
+    (OrderExecutedWithPrice { reference, executed_shares, match_number, printable, execution_price })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderCancel {
+    reference: u64,
+    cancelled_shares: u32,
+}
+
+named!(parse_order_cancel<OrderCancel>, do_parse!(
+    reference: be_u64 >>
+    cancelled_shares: be_u32 >>
+    (OrderCancel { reference, cancelled_shares })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    reference: u64,
+    side: Side,
+    shares: u32,
+    stock: ArrayString<[u8; 8]>,
+    price: Price4,
+    match_number: u64,
+}
+
+named!(parse_trade<Trade>, do_parse!(
+    reference: be_u64 >>
+    side: parse_side >>
+    shares: be_u32 >>
+    stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
+    price: map!(be_u32, Price4::from_raw) >>
+    match_number: be_u64 >>
+    (Trade { reference, side, shares, stock, price, match_number })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossTrade {
+    shares: u64,
+    stock: ArrayString<[u8; 8]>,
+    cross_price: Price4,
+    match_number: u64,
+    cross_type: CrossType,
+}
+
+named!(parse_cross_trade<CrossTrade>, do_parse!(
+    shares: be_u64 >>
+    stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
+    cross_price: map!(be_u32, Price4::from_raw) >>
+    match_number: be_u64 >>
+    cross_type: parse_cross_type >>
+    (CrossTrade { shares, stock, cross_price, match_number, cross_type })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Noii {
+    paired_shares: u64,
+    imbalance_shares: u64,
+    imbalance_direction: ImbalanceDirection,
+    stock: ArrayString<[u8; 8]>,
+    far_price: Price4,
+    near_price: Price4,
+    current_reference_price: Price4,
+    cross_type: CrossType,
+    price_variation_indicator: char,
+}
+
+named!(parse_noii<Noii>, do_parse!(
+    paired_shares: be_u64 >>
+    imbalance_shares: be_u64 >>
+    imbalance_direction: parse_imbalance_direction >>
+    stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
+    far_price: map!(be_u32, Price4::from_raw) >>
+    near_price: map!(be_u32, Price4::from_raw) >>
+    current_reference_price: map!(be_u32, Price4::from_raw) >>
+    cross_type: parse_cross_type >>
+    price_variation_indicator: map!(be_u8, |b| b as char) >>
+    (Noii {
+        paired_shares, imbalance_shares, imbalance_direction, stock,
+        far_price, near_price, current_reference_price, cross_type,
+        price_variation_indicator
+    })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MwcbDeclineLevel {
+    level_1: u64,
+    level_2: u64,
+    level_3: u64,
+}
+
+named!(parse_mwcb_decline_level<MwcbDeclineLevel>, do_parse!(
+    level_1: be_u64 >>
+    level_2: be_u64 >>
+    level_3: be_u64 >>
+    (MwcbDeclineLevel { level_1, level_2, level_3 })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MwcbBreach {
+    breached_level: BreachedLevel,
+}
+
+named!(parse_mwcb_breach<MwcbBreach>, do_parse!(
+    breached_level: parse_breached_level >>
+    (MwcbBreach { breached_level })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpoQuotingPeriod {
+    stock: ArrayString<[u8; 8]>,
+    release_time: u32,
+    release_qualifier: IpoReleaseQualifier,
+    ipo_price: Price4,
+}
+
+named!(parse_ipo_quoting_period<IpoQuotingPeriod>, do_parse!(
+    stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
+    release_time: be_u32 >>
+    release_qualifier: parse_ipo_release_qualifier >>
+    ipo_price: map!(be_u32, Price4::from_raw) >>
+    (IpoQuotingPeriod { stock, release_time, release_qualifier, ipo_price })
+));
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuldAuctionCollar {
+    stock: ArrayString<[u8; 8]>,
+    reference_price: Price4,
+    upper_price: Price4,
+    lower_price: Price4,
+    extension: u32,
+}
+
+named!(parse_luld_auction_collar<LuldAuctionCollar>, do_parse!(
+    stock: map!(take_str!(8), |s| ArrayString::from(s).unwrap()) >>
+    reference_price: map!(be_u32, Price4::from_raw) >>
+    upper_price: map!(be_u32, Price4::from_raw) >>
+    lower_price: map!(be_u32, Price4::from_raw) >>
+    extension: be_u32 >>
+    (LuldAuctionCollar { stock, reference_price, upper_price, lower_price, extension })
+));
+
 
 #[cfg(test)]
 mod tests {
@@ -462,7 +1003,7 @@ mod tests {
 
     #[test]
     fn check_sizeof() {
-        assert_eq!(std::mem::size_of::<Message>(), 56)
+        assert_eq!(std::mem::size_of::<Message>(), 72)
     }
 
     #[test]
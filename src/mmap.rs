@@ -0,0 +1,428 @@
+//! Zero-copy parsing directly against a memory-mapped file.
+//!
+//! `MessageStream` copies bytes through a fixed-size circular `buffer`,
+//! which works for any `Read` but means every message is copied at least
+//! once and the buffer has to be large enough to hold the longest message
+//! that can straddle a read boundary. When the input is a plain seekable
+//! file, none of that is necessary: mapping the whole file and parsing
+//! straight out of the mapped `&[u8]` avoids both the read syscalls and the
+//! copy, and lets `MessageRef` borrow its variable-length fields (the
+//! `Unknown` payload, ITCH stock/mpid/reason strings) from the map instead
+//! of allocating.
+
+use std::fs::File;
+use std::path::Path;
+
+use memmap::Mmap;
+use nom::{be_u16, be_u32, be_u64, be_u8, IResult};
+
+use errors::*;
+use {
+    char2bool, maybe_char2bool, parse_authenticity, parse_breached_level, parse_cross_type,
+    parse_event_code, parse_financial_status, parse_imbalance_direction,
+    parse_ipo_release_qualifier, parse_issue_classification, parse_issue_sub_type,
+    parse_luld_ref_price_tier, parse_market_category, parse_market_maker_mode,
+    parse_market_participant_state, parse_message_header, parse_reg_sho_action, parse_side,
+    parse_trading_state, CrossType, EventCode, FinancialStatus, ImbalanceDirection,
+    IpoReleaseQualifier, IssueClassification, IssueSubType, LuldRefPriceTier, MarketCategory,
+    MarketMakerMode, MarketParticipantState, MsgHeader, MwcbBreach, MwcbDeclineLevel,
+    OrderCancel, OrderExecuted, OrderExecutedWithPrice, Price4, RegShoAction, ReplaceOrder, Side,
+    TradingState,
+};
+
+/// Borrowed, allocation-free counterpart of `Message`: every variable-length
+/// field points directly into the memory-mapped file instead of owning a
+/// copy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageRef<'a> {
+    pub header: MsgHeader,
+    pub body: MessageBodyRef<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageBodyRef<'a> {
+    AddOrder(AddOrderRef<'a>),
+    AddOrderMpid(AddOrderMpidRef<'a>),
+    ReplaceOrder(ReplaceOrder),
+    DeleteOrder { reference: u64 },
+    SystemEvent { event: EventCode },
+    RegShoRestriction {
+        stock: &'a str,
+        action: RegShoAction,
+    },
+    TradingAction {
+        stock: &'a str,
+        trading_state: TradingState,
+        reason: &'a str,
+    },
+    StockDirectory(StockDirectoryRef<'a>),
+    ParticipantPosition(MarketParticipantPositionRef<'a>),
+    OrderExecuted(OrderExecuted),
+    OrderExecutedWithPrice(OrderExecutedWithPrice),
+    OrderCancel(OrderCancel),
+    Trade(TradeRef<'a>),
+    CrossTrade(CrossTradeRef<'a>),
+    BrokenTrade { match_number: u64 },
+    Noii(NoiiRef<'a>),
+    MwcbDeclineLevel(MwcbDeclineLevel),
+    MwcbBreach(MwcbBreach),
+    IpoQuotingPeriod(IpoQuotingPeriodRef<'a>),
+    LuldAuctionCollar(LuldAuctionCollarRef<'a>),
+    Unknown {
+        length: u16,
+        tag: char,
+        content: &'a [u8],
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StockDirectoryRef<'a> {
+    pub stock: &'a str,
+    pub market_category: MarketCategory,
+    pub financial_status: FinancialStatus,
+    pub round_lot_size: u32,
+    pub round_lots_only: bool,
+    pub issue_classification: IssueClassification,
+    pub issue_subtype: IssueSubType,
+    pub authenticity: bool,
+    pub short_sale_threshold: Option<bool>,
+    pub ipo_flag: Option<bool>,
+    pub luld_ref_price_tier: LuldRefPriceTier,
+    pub etp_flag: Option<bool>,
+    pub etp_leverage_factor: u32,
+    pub inverse_indicator: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketParticipantPositionRef<'a> {
+    pub mpid: &'a str,
+    pub stock: &'a str,
+    pub primary_market_maker: bool,
+    pub market_maker_mode: MarketMakerMode,
+    pub market_participant_state: MarketParticipantState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddOrderRef<'a> {
+    pub reference: u64,
+    pub side: Side,
+    pub shares: u32,
+    pub stock: &'a str,
+    pub price: Price4,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddOrderMpidRef<'a> {
+    pub reference: u64,
+    pub side: Side,
+    pub shares: u32,
+    pub stock: &'a str,
+    pub price: Price4,
+    pub mpid: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeRef<'a> {
+    pub reference: u64,
+    pub side: Side,
+    pub shares: u32,
+    pub stock: &'a str,
+    pub price: Price4,
+    pub match_number: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossTradeRef<'a> {
+    pub shares: u64,
+    pub stock: &'a str,
+    pub cross_price: Price4,
+    pub match_number: u64,
+    pub cross_type: CrossType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiiRef<'a> {
+    pub paired_shares: u64,
+    pub imbalance_shares: u64,
+    pub imbalance_direction: ImbalanceDirection,
+    pub stock: &'a str,
+    pub far_price: Price4,
+    pub near_price: Price4,
+    pub current_reference_price: Price4,
+    pub cross_type: CrossType,
+    pub price_variation_indicator: char,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpoQuotingPeriodRef<'a> {
+    pub stock: &'a str,
+    pub release_time: u32,
+    pub release_qualifier: IpoReleaseQualifier,
+    pub ipo_price: Price4,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuldAuctionCollarRef<'a> {
+    pub stock: &'a str,
+    pub reference_price: Price4,
+    pub upper_price: Price4,
+    pub lower_price: Price4,
+    pub extension: u32,
+}
+
+named!(parse_stock_directory_ref<StockDirectoryRef<'_>>, do_parse!(
+    stock: take_str!(8) >>
+    market_category: parse_market_category >>
+    financial_status: parse_financial_status >>
+    round_lot_size: be_u32 >>
+    round_lots_only: char2bool >>
+    issue_classification: parse_issue_classification >>
+    issue_subtype: parse_issue_sub_type >>
+    authenticity: parse_authenticity >>
+    short_sale_threshold: maybe_char2bool >>
+    ipo_flag: maybe_char2bool >>
+    luld_ref_price_tier: parse_luld_ref_price_tier >>
+    etp_flag: maybe_char2bool >>
+    etp_leverage_factor: be_u32 >>
+    inverse_indicator: char2bool >>
+    (StockDirectoryRef {
+        stock, market_category, financial_status, round_lot_size,
+        round_lots_only, issue_classification, issue_subtype,
+        authenticity, short_sale_threshold, ipo_flag,
+        luld_ref_price_tier, etp_flag, etp_leverage_factor, inverse_indicator
+    })
+));
+
+named!(parse_participant_position_ref<MarketParticipantPositionRef<'_>>, do_parse!(
+    mpid: take_str!(4) >>
+    stock: take_str!(8) >>
+    primary_market_maker: char2bool >>
+    market_maker_mode: parse_market_maker_mode >>
+    market_participant_state: parse_market_participant_state >>
+    (MarketParticipantPositionRef {
+        mpid, stock, primary_market_maker, market_maker_mode, market_participant_state
+    })
+));
+
+named!(parse_reg_sho_restriction_ref<MessageBodyRef<'_>>, do_parse!(
+    stock: take_str!(8) >>
+    action: parse_reg_sho_action >>
+    (MessageBodyRef::RegShoRestriction { stock, action })
+));
+
+named!(parse_trading_action_ref<MessageBodyRef<'_>>, do_parse!(
+    stock: take_str!(8) >>
+    trading_state: parse_trading_state >>
+    be_u8 >> // skip reserved byte
+    reason: take_str!(4) >>
+    (MessageBodyRef::TradingAction { stock, trading_state, reason })
+));
+
+named!(parse_add_order_ref<AddOrderRef<'_>>, do_parse!(
+    reference: be_u64 >>
+    side: parse_side >>
+    shares: be_u32 >>
+    stock: take_str!(8) >>
+    price: map!(be_u32, Price4::from_raw) >>
+    (AddOrderRef { reference, side, shares, stock, price })
+));
+
+named!(parse_add_order_mpid_ref<AddOrderMpidRef<'_>>, do_parse!(
+    reference: be_u64 >>
+    side: parse_side >>
+    shares: be_u32 >>
+    stock: take_str!(8) >>
+    price: map!(be_u32, Price4::from_raw) >>
+    mpid: take_str!(4) >>
+    (AddOrderMpidRef { reference, side, shares, stock, price, mpid })
+));
+
+named!(parse_replace_order_ref<ReplaceOrder>, do_parse!(
+    old_reference: be_u64 >>
+    new_reference: be_u64 >>
+    shares: be_u32 >>
+    price: map!(be_u32, Price4::from_raw) >>
+    (ReplaceOrder { old_reference, new_reference, shares, price })
+));
+
+named!(parse_order_executed_ref<OrderExecuted>, do_parse!(
+    reference: be_u64 >>
+    executed_shares: be_u32 >>
+    match_number: be_u64 >>
+    (OrderExecuted { reference, executed_shares, match_number })
+));
+
+named!(parse_order_executed_with_price_ref<OrderExecutedWithPrice>, do_parse!(
+    reference: be_u64 >>
+    executed_shares: be_u32 >>
+    match_number: be_u64 >>
+    printable: char2bool >>
+    execution_price: map!(be_u32, Price4::from_raw) >>
+    (OrderExecutedWithPrice { reference, executed_shares, match_number, printable, execution_price })
+));
+
+named!(parse_order_cancel_ref<OrderCancel>, do_parse!(
+    reference: be_u64 >>
+    cancelled_shares: be_u32 >>
+    (OrderCancel { reference, cancelled_shares })
+));
+
+named!(parse_trade_ref<TradeRef<'_>>, do_parse!(
+    reference: be_u64 >>
+    side: parse_side >>
+    shares: be_u32 >>
+    stock: take_str!(8) >>
+    price: map!(be_u32, Price4::from_raw) >>
+    match_number: be_u64 >>
+    (TradeRef { reference, side, shares, stock, price, match_number })
+));
+
+named!(parse_cross_trade_ref<CrossTradeRef<'_>>, do_parse!(
+    shares: be_u64 >>
+    stock: take_str!(8) >>
+    cross_price: map!(be_u32, Price4::from_raw) >>
+    match_number: be_u64 >>
+    cross_type: parse_cross_type >>
+    (CrossTradeRef { shares, stock, cross_price, match_number, cross_type })
+));
+
+named!(parse_noii_ref<NoiiRef<'_>>, do_parse!(
+    paired_shares: be_u64 >>
+    imbalance_shares: be_u64 >>
+    imbalance_direction: parse_imbalance_direction >>
+    stock: take_str!(8) >>
+    far_price: map!(be_u32, Price4::from_raw) >>
+    near_price: map!(be_u32, Price4::from_raw) >>
+    current_reference_price: map!(be_u32, Price4::from_raw) >>
+    cross_type: parse_cross_type >>
+    price_variation_indicator: map!(be_u8, |b| b as char) >>
+    (NoiiRef {
+        paired_shares, imbalance_shares, imbalance_direction, stock,
+        far_price, near_price, current_reference_price, cross_type,
+        price_variation_indicator
+    })
+));
+
+named!(parse_mwcb_decline_level_ref<MwcbDeclineLevel>, do_parse!(
+    level_1: be_u64 >>
+    level_2: be_u64 >>
+    level_3: be_u64 >>
+    (MwcbDeclineLevel { level_1, level_2, level_3 })
+));
+
+named!(parse_mwcb_breach_ref<MwcbBreach>, do_parse!(
+    breached_level: parse_breached_level >>
+    (MwcbBreach { breached_level })
+));
+
+named!(parse_ipo_quoting_period_ref<IpoQuotingPeriodRef<'_>>, do_parse!(
+    stock: take_str!(8) >>
+    release_time: be_u32 >>
+    release_qualifier: parse_ipo_release_qualifier >>
+    ipo_price: map!(be_u32, Price4::from_raw) >>
+    (IpoQuotingPeriodRef { stock, release_time, release_qualifier, ipo_price })
+));
+
+named!(parse_luld_auction_collar_ref<LuldAuctionCollarRef<'_>>, do_parse!(
+    stock: take_str!(8) >>
+    reference_price: map!(be_u32, Price4::from_raw) >>
+    upper_price: map!(be_u32, Price4::from_raw) >>
+    lower_price: map!(be_u32, Price4::from_raw) >>
+    extension: be_u32 >>
+    (LuldAuctionCollarRef { stock, reference_price, upper_price, lower_price, extension })
+));
+
+named!(parse_message_ref<MessageRef<'_>>, do_parse!(
+    length: be_u16 >>
+    message: call!(parse_message_body_ref, length) >>
+    (message)
+));
+
+fn parse_message_body_ref(input: &[u8], length: u16) -> IResult<&[u8], MessageRef<'_>> {
+    do_parse!(input,
+        tag: be_u8 >>
+        header: parse_message_header >>
+        body: switch!(value!(tag),
+            b'S' => map!(parse_event_code, |event| MessageBodyRef::SystemEvent { event }) |
+            b'R' => map!(parse_stock_directory_ref, MessageBodyRef::StockDirectory) |
+            b'L' => map!(parse_participant_position_ref, MessageBodyRef::ParticipantPosition) |
+            b'Y' => call!(parse_reg_sho_restriction_ref) |
+            b'H' => call!(parse_trading_action_ref) |
+            b'A' => map!(parse_add_order_ref, MessageBodyRef::AddOrder) |
+            b'F' => map!(parse_add_order_mpid_ref, MessageBodyRef::AddOrderMpid) |
+            b'U' => map!(parse_replace_order_ref, MessageBodyRef::ReplaceOrder) |
+            b'D' => map!(be_u64, |reference| MessageBodyRef::DeleteOrder{ reference }) |
+            b'E' => map!(parse_order_executed_ref, MessageBodyRef::OrderExecuted) |
+            b'C' => map!(parse_order_executed_with_price_ref, MessageBodyRef::OrderExecutedWithPrice) |
+            b'X' => map!(parse_order_cancel_ref, MessageBodyRef::OrderCancel) |
+            b'P' => map!(parse_trade_ref, MessageBodyRef::Trade) |
+            b'Q' => map!(parse_cross_trade_ref, MessageBodyRef::CrossTrade) |
+            b'B' => map!(be_u64, |match_number| MessageBodyRef::BrokenTrade { match_number }) |
+            b'I' => map!(parse_noii_ref, MessageBodyRef::Noii) |
+            b'V' => map!(parse_mwcb_decline_level_ref, MessageBodyRef::MwcbDeclineLevel) |
+            b'W' => map!(parse_mwcb_breach_ref, MessageBodyRef::MwcbBreach) |
+            b'K' => map!(parse_ipo_quoting_period_ref, MessageBodyRef::IpoQuotingPeriod) |
+            b'J' => map!(parse_luld_auction_collar_ref, MessageBodyRef::LuldAuctionCollar) |
+            other => map!(take!(length - 11),    // tag + header = 11
+                          |slice| MessageBodyRef::Unknown {
+                              length, tag: other as char, content: slice
+            })) >>
+        (MessageRef { header, body })
+    )
+}
+
+/// A file mapped into memory so it can be parsed with zero per-message
+/// allocation. Holds the mapping alive; call `messages()` to iterate.
+pub struct MmapFile {
+    mmap: Mmap,
+}
+
+impl MmapFile {
+    pub fn messages(&self) -> MmapMessages<'_> {
+        MmapMessages {
+            data: &self.mmap[..],
+            position: 0,
+        }
+    }
+}
+
+/// Iterates the length-prefixed ITCH messages directly out of a mapped
+/// file's bytes, borrowing every variable-length field instead of copying
+/// it.
+pub struct MmapMessages<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for MmapMessages<'a> {
+    type Item = Result<MessageRef<'a>>;
+
+    fn next(&mut self) -> Option<Result<MessageRef<'a>>> {
+        use nom::IResult::*;
+
+        if self.position >= self.data.len() {
+            return None;
+        }
+
+        let input = &self.data[self.position..];
+        match parse_message_ref(input) {
+            Done(rest, message) => {
+                self.position += input.len() - rest.len();
+                Some(Ok(message))
+            }
+            Error(e) => Some(Err(format!("Parse failed: {}", e).into())),
+            Incomplete(_) => Some(Err("Unexpected EOF: truncated message at end of mapped file".into())),
+        }
+    }
+}
+
+/// Memory-maps `path` and returns a handle that can be iterated with
+/// `MmapFile::messages()` without copying any message bytes, unlike
+/// `parse_file`'s streaming `MessageStream`. Only suitable for seekable,
+/// already-complete files -- use `parse_reader`/`parse_file` for
+/// non-seekable or still-growing inputs.
+pub fn parse_mmap<P: AsRef<Path>>(path: P) -> Result<MmapFile> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    Ok(MmapFile { mmap })
+}
@@ -0,0 +1,391 @@
+//! MoldUDP64 framing and pcap/pcapng replay support.
+//!
+//! Real TotalView-ITCH feeds are not delivered as one contiguous stream of
+//! length-prefixed messages; they arrive as UDP multicast datagrams wrapped
+//! in the MoldUDP64 session-layer protocol, and captures of that traffic are
+//! stored as pcap/pcapng files. This module layers that framing on top of
+//! the plain `parse_message_body` decoder so captured multicast data can be
+//! replayed directly.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use arrayvec::ArrayString;
+use nom::{be_u16, be_u64, be_u8, le_u32, IResult};
+
+use errors::*;
+use {parse_message_body, Message};
+
+const HEARTBEAT: u16 = 0;
+const END_OF_SESSION: u16 = 0xFFFF;
+
+/// An ITCH message decoded from a MoldUDP64 session, tagged with the
+/// sequence number it occupies in that session so that callers can detect
+/// gaps (dropped packets) across the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoldMessage {
+    pub sequence: u64,
+    pub message: Message,
+}
+
+named!(parse_mold_header<(ArrayString<[u8; 10]>, u64, u16)>, do_parse!(
+    session: map!(take_str!(10), |s| ArrayString::from(s).unwrap()) >>
+    sequence: be_u64 >>
+    message_count: be_u16 >>
+    (session, sequence, message_count)
+));
+
+named!(parse_mold_block<&[u8]>, do_parse!(
+    length: be_u16 >>
+    block: take!(length) >>
+    (block)
+));
+
+/// Iterates the length-prefixed message blocks inside a single MoldUDP64
+/// downstream packet, yielding each decoded `Message` paired with its
+/// sequence number.
+///
+/// A `message_count` of `0` marks a heartbeat packet (no payload, sequence
+/// just advances) and `0xFFFF` marks the end of the session; both yield no
+/// items from this iterator.
+pub struct MoldStream<'a> {
+    session: ArrayString<[u8; 10]>,
+    sequence: u64,
+    message_count: u16,
+    delivered: u16,
+    rest: &'a [u8],
+}
+
+impl<'a> MoldStream<'a> {
+    pub fn new(packet: &'a [u8]) -> Result<MoldStream<'a>> {
+        use nom::IResult::*;
+        match parse_mold_header(packet) {
+            Done(rest, (session, sequence, message_count)) => Ok(MoldStream {
+                session,
+                sequence,
+                message_count,
+                delivered: 0,
+                rest,
+            }),
+            Error(e) => Err(format!("Failed to parse MoldUDP64 header: {}", e).into()),
+            Incomplete(_) => Err("MoldUDP64 packet too short for its header".into()),
+        }
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    pub fn is_heartbeat(&self) -> bool {
+        self.message_count == HEARTBEAT
+    }
+
+    pub fn is_end_of_session(&self) -> bool {
+        self.message_count == END_OF_SESSION
+    }
+}
+
+impl<'a> Iterator for MoldStream<'a> {
+    type Item = Result<MoldMessage>;
+
+    fn next(&mut self) -> Option<Result<MoldMessage>> {
+        use nom::IResult::*;
+
+        if self.is_heartbeat() || self.is_end_of_session() || self.delivered >= self.message_count {
+            return None;
+        }
+
+        let sequence = self.sequence + self.delivered as u64;
+        match parse_mold_block(self.rest) {
+            Done(rest, block) => {
+                self.rest = rest;
+                self.delivered += 1;
+                let length = block.len() as u16;
+                match parse_message_body(block, length) {
+                    Done(_, message) => Some(Ok(MoldMessage { sequence, message })),
+                    Error(e) => Some(Err(format!("Parse failed: {}", e).into())),
+                    Incomplete(_) => Some(Err("Truncated ITCH message in MoldUDP64 block".into())),
+                }
+            }
+            Error(e) => Some(Err(format!("Failed to parse MoldUDP64 block: {}", e).into())),
+            Incomplete(_) => Some(Err("MoldUDP64 packet truncated before its message blocks".into())),
+        }
+    }
+}
+
+// --- pcapng + Ethernet/IP/UDP framing, just enough to get at the UDP payload ---
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const IP_PROTO_UDP: u8 = 17;
+
+fn rest(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    IResult::Done(&input[input.len()..], input)
+}
+
+named!(parse_section_header_block<()>, do_parse!(
+    tag!(&SECTION_HEADER_BLOCK.to_le_bytes()[..]) >>
+    block_length: le_u32 >>
+    verify!(le_u32, |magic| magic == BYTE_ORDER_MAGIC) >>
+    take!(block_length - 12) >> // versions, section length, options, trailing length
+    (())
+));
+
+named!(parse_unknown_block<()>, do_parse!(
+    le_u32 >> // block type, already checked by the caller
+    block_length: le_u32 >>
+    take!(block_length - 8) >>
+    (())
+));
+
+fn padded_len(len: u32) -> u32 {
+    (len + 3) & !3
+}
+
+named!(parse_enhanced_packet_block<&[u8]>, do_parse!(
+    tag!(&ENHANCED_PACKET_BLOCK.to_le_bytes()[..]) >>
+    block_length: le_u32 >>
+    le_u32 >> // interface id
+    le_u32 >> // timestamp (high)
+    le_u32 >> // timestamp (low)
+    captured_length: le_u32 >>
+    le_u32 >> // original length
+    packet: take!(captured_length) >>
+    take!(padded_len(captured_length) - captured_length) >> // alignment padding
+    take!(block_length - 28 - padded_len(captured_length)) >> // options + trailing length
+    (packet)
+));
+
+named!(parse_ethernet_payload<(u16, &[u8])>, do_parse!(
+    take!(12) >> // destination + source MAC addresses
+    ethertype: be_u16 >>
+    result: switch!(value!(ethertype),
+        ETHERTYPE_VLAN => do_parse!(
+            be_u16 >>
+            inner_ethertype: be_u16 >>
+            payload: call!(rest) >>
+            ((inner_ethertype, payload))
+        ) |
+        _ => do_parse!(payload: call!(rest) >> ((ethertype, payload)))
+    ) >>
+    (result)
+));
+
+named!(parse_ipv4_header<(u8, &[u8])>, do_parse!(
+    version_ihl: be_u8 >>
+    take!(8) >> // DSCP/ECN, total length, identification, flags/fragment offset, TTL
+    protocol: be_u8 >>
+    take!(2) >> // header checksum
+    take!(8) >> // source + destination address
+    take!((((version_ihl & 0x0F) as usize) * 4).saturating_sub(20)) >> // IP options
+    payload: call!(rest) >>
+    (protocol, payload)
+));
+
+named!(parse_udp_payload<&[u8]>, do_parse!(
+    take!(4) >> // source + destination port
+    length: be_u16 >>
+    take!(2) >> // checksum
+    payload: take!(length.saturating_sub(8)) >>
+    (payload)
+));
+
+/// Strips the Ethernet/IP/UDP headers off a single captured frame and
+/// returns the UDP payload (a MoldUDP64 packet), or `None` if the frame
+/// isn't UDP at all -- a capture of a multicast group routinely carries
+/// IGMP, ARP, and other neighboring traffic alongside the ITCH feed, and
+/// that's not an error, just not interesting here. A frame that looks like
+/// it should be UDP but is truncated partway through still surfaces as an
+/// `Err`, since that indicates real capture corruption.
+fn udp_payload(frame: &[u8]) -> Result<Option<&[u8]>> {
+    use nom::IResult::*;
+
+    let (ethertype, ip_packet) = match parse_ethernet_payload(frame) {
+        Done(_, result) => result,
+        _ => bail!("Truncated Ethernet frame"),
+    };
+    if ethertype != ETHERTYPE_IPV4 {
+        return Ok(None);
+    }
+    let (protocol, segment) = match parse_ipv4_header(ip_packet) {
+        Done(_, result) => result,
+        _ => bail!("Truncated IPv4 header"),
+    };
+    if protocol != IP_PROTO_UDP {
+        return Ok(None);
+    }
+    match parse_udp_payload(segment) {
+        Done(_, payload) => Ok(Some(payload)),
+        _ => bail!("Truncated UDP header"),
+    }
+}
+
+fn decode_mold_packet(payload: &[u8]) -> Result<Vec<Result<MoldMessage>>> {
+    Ok(MoldStream::new(payload)?.collect())
+}
+
+/// Replays ITCH messages out of a pcapng capture of MoldUDP64 multicast
+/// traffic, one Enhanced Packet Block at a time.
+///
+/// Only little-endian pcapng sections are supported, which covers the
+/// overwhelming majority of captures taken on x86 hosts.
+pub struct PcapStream {
+    data: Vec<u8>,
+    position: usize,
+    pending: VecDeque<Result<MoldMessage>>,
+}
+
+impl PcapStream {
+    fn new(data: Vec<u8>) -> Result<PcapStream> {
+        use nom::IResult::*;
+        let position = match parse_section_header_block(&data) {
+            Done(rest, ()) => data.len() - rest.len(),
+            _ => bail!("Not a pcapng capture (missing Section Header Block)"),
+        };
+        Ok(PcapStream {
+            data,
+            position,
+            pending: VecDeque::new(),
+        })
+    }
+
+    // Advances through pcapng blocks until at least one message (or error)
+    // is queued up, returning `false` once the capture is exhausted.
+    fn fill_pending(&mut self) -> bool {
+        use nom::IResult::*;
+
+        while self.position < self.data.len() {
+            let input = &self.data[self.position..];
+            let input_len = input.len();
+            let block_type = match le_u32(input) {
+                Done(_, t) => t,
+                _ => {
+                    self.pending.push_back(Err("Truncated pcapng block header".into()));
+                    self.position = self.data.len();
+                    return true;
+                }
+            };
+
+            if block_type == ENHANCED_PACKET_BLOCK {
+                match parse_enhanced_packet_block(input) {
+                    Done(rest, frame) => {
+                        self.position += input_len - rest.len();
+                        match udp_payload(frame) {
+                            Ok(Some(payload)) => match decode_mold_packet(payload) {
+                                Ok(messages) => {
+                                    if !messages.is_empty() {
+                                        self.pending.extend(messages);
+                                        return true;
+                                    }
+                                    // heartbeat / end-of-session packet: keep scanning
+                                }
+                                // A UDP payload that isn't actually
+                                // MoldUDP64 -- some other protocol sharing
+                                // the multicast group/port. Not an error,
+                                // just not ours: keep scanning.
+                                Err(_) => (),
+                            },
+                            // Not a UDP packet at all (IGMP, ARP, a
+                            // different IP protocol, ...): keep scanning.
+                            Ok(None) => (),
+                            Err(e) => {
+                                self.pending.push_back(Err(e));
+                                return true;
+                            }
+                        }
+                    }
+                    _ => {
+                        self.pending.push_back(Err("Malformed Enhanced Packet Block".into()));
+                        return true;
+                    }
+                }
+            } else {
+                match parse_unknown_block(input) {
+                    Done(rest, ()) => self.position += input_len - rest.len(),
+                    _ => {
+                        self.pending.push_back(Err("Malformed pcapng block".into()));
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Iterator for PcapStream {
+    type Item = Result<MoldMessage>;
+
+    fn next(&mut self) -> Option<Result<MoldMessage>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if !self.fill_pending() {
+                return None;
+            }
+        }
+    }
+}
+
+/// Replays a pcapng capture of MoldUDP64 multicast traffic as a flat
+/// iterator of decoded messages, exactly like `parse_file`/`parse_gzip` do
+/// for raw ITCH files -- except each item also carries the MoldUDP64
+/// sequence number it was delivered at.
+pub fn parse_pcap<P: AsRef<Path>>(path: P) -> Result<PcapStream> {
+    let mut file = File::open(path)?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    PcapStream::new(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat_packet() -> Vec<u8> {
+        let mut packet = b"SESSION001".to_vec();
+        packet.extend_from_slice(&0u64.to_be_bytes()); // sequence
+        packet.extend_from_slice(&0u16.to_be_bytes()); // heartbeat: no message blocks
+        packet
+    }
+
+    fn single_message_packet() -> Vec<u8> {
+        let mut packet = b"SESSION001".to_vec();
+        packet.extend_from_slice(&7u64.to_be_bytes()); // sequence
+        packet.extend_from_slice(&1u16.to_be_bytes()); // one message block
+        let message: &[u8] = &[
+            b'S', // system event
+            0x00, 0x00, // stock_locate
+            0x00, 0x00, // tracking_number
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // timestamp
+            b'O', // event code: start of messages
+        ];
+        packet.extend_from_slice(&(message.len() as u16).to_be_bytes());
+        packet.extend_from_slice(message);
+        packet
+    }
+
+    #[test]
+    fn heartbeat_yields_nothing() {
+        let packet = heartbeat_packet();
+        let stream = MoldStream::new(&packet).unwrap();
+        assert!(stream.is_heartbeat());
+        assert_eq!(stream.count(), 0);
+    }
+
+    #[test]
+    fn single_message_carries_sequence() {
+        let packet = single_message_packet();
+        let stream = MoldStream::new(&packet).unwrap();
+        let messages: Vec<_> = stream.map(|m| m.unwrap()).collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].sequence, 7);
+    }
+}
@@ -0,0 +1,94 @@
+//! Fixed-point representation of ITCH prices.
+//!
+//! ITCH encodes prices as `u32`s with four implied decimal places -- the
+//! wire value `1234567` means `123.4567`. Converting straight to `f64` on
+//! the way in throws away that exactness the moment downstream code sums
+//! or diffs prices, so `Price4` keeps the scaled integer around and only
+//! converts to a float (or `Decimal`) at the point where a caller actually
+//! asks for one. `Add`/`Sub` have the same overflow/underflow behavior as
+//! the underlying `u32`; use `checked_add`/`checked_sub` where the inputs
+//! aren't already known to fit.
+
+use std::fmt;
+use std::ops::{Add, Sub};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Price4(u32);
+
+impl Price4 {
+    const SCALE: u32 = 10_000;
+
+    /// Wraps a raw wire value, already scaled by `10,000`.
+    pub fn from_raw(raw: u32) -> Price4 {
+        Price4(raw)
+    }
+
+    /// The raw wire value, still scaled by `10,000` -- use this (or `Add`/
+    /// `Sub`) for order-book arithmetic instead of converting to `f64`.
+    pub fn raw(&self) -> u32 {
+        self.0
+    }
+
+    /// This price as a floating-point dollar amount. Prefer `raw` or the
+    /// `Decimal` conversion for arithmetic; this is meant for display and
+    /// interop with code that only deals in floats.
+    pub fn as_f64(&self) -> f64 {
+        f64::from(self.0) / f64::from(Price4::SCALE)
+    }
+
+    /// Adds two prices, returning `None` on overflow instead of wrapping.
+    pub fn checked_add(self, rhs: Price4) -> Option<Price4> {
+        self.0.checked_add(rhs.0).map(Price4)
+    }
+
+    /// Subtracts two prices, returning `None` if `rhs` is larger than `self`
+    /// instead of underflowing.
+    pub fn checked_sub(self, rhs: Price4) -> Option<Price4> {
+        self.0.checked_sub(rhs.0).map(Price4)
+    }
+}
+
+impl fmt::Display for Price4 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:04}", self.0 / Price4::SCALE, self.0 % Price4::SCALE)
+    }
+}
+
+/// Panics on overflow in debug builds, wraps in release -- the same
+/// semantics as the underlying `u32`. Use `checked_add` if the inputs
+/// aren't already known to fit.
+impl Add for Price4 {
+    type Output = Price4;
+
+    fn add(self, rhs: Price4) -> Price4 {
+        Price4(self.0 + rhs.0)
+    }
+}
+
+/// Panics on underflow in debug builds, wraps in release -- the same
+/// semantics as the underlying `u32`. Use `checked_sub` if `rhs` isn't
+/// already known to be no larger than `self` (e.g. an ordinary
+/// `lower - upper` on unrelated prices).
+impl Sub for Price4 {
+    type Output = Price4;
+
+    fn sub(self, rhs: Price4) -> Price4 {
+        Price4(self.0 - rhs.0)
+    }
+}
+
+impl From<Price4> for f64 {
+    fn from(price: Price4) -> f64 {
+        price.as_f64()
+    }
+}
+
+/// Lossless conversion to `rust_decimal`'s arbitrary-precision type, for
+/// callers that want exact decimal arithmetic without pulling in `f64`.
+#[cfg(feature = "rust_decimal")]
+impl From<Price4> for ::rust_decimal::Decimal {
+    fn from(price: Price4) -> ::rust_decimal::Decimal {
+        ::rust_decimal::Decimal::new(i64::from(price.0), 4)
+    }
+}